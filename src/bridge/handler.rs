@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use nvim_rs::{Handler, Neovim};
+use rmpv::Value;
+
+use super::NeovimWriter;
+
+type RequestFn = fn(&[Value]) -> Result<Value, Value>;
+
+lazy_static! {
+    /// Registration table for `rpcrequest(channel, name, ...)` calls. Adding
+    /// a new GUI-side method means adding an entry here - `dispatch_request`
+    /// itself never needs to change.
+    static ref REQUEST_HANDLERS: HashMap<&'static str, RequestFn> = {
+        let mut handlers: HashMap<&'static str, RequestFn> = HashMap::new();
+        handlers.insert("ping", ping);
+        handlers.insert("neovide_get_font", get_font);
+        handlers.insert("neovide_get_scale_factor", get_scale_factor);
+        handlers
+    };
+}
+
+fn ping(_args: &[Value]) -> Result<Value, Value> {
+    Ok(Value::from("pong"))
+}
+
+fn get_font(_args: &[Value]) -> Result<Value, Value> {
+    Ok(Value::from(
+        std::env::var("NEOVIDE_FONT").unwrap_or_else(|_| "Fira Code".to_string()),
+    ))
+}
+
+fn get_scale_factor(_args: &[Value]) -> Result<Value, Value> {
+    Ok(Value::from(
+        std::env::var("NEOVIDE_SCALE_FACTOR")
+            .ok()
+            .and_then(|value| value.parse::<f64>().ok())
+            .unwrap_or(1.0),
+    ))
+}
+
+/// Handles RPC traffic coming out of the `nvim` process. Blocking requests
+/// issued via `rpcrequest` from Lua or VimL are answered from
+/// `REQUEST_HANDLERS`; notifications (redraw events, `rpcnotify` calls) are
+/// accepted and ignored here.
+#[derive(Clone)]
+pub struct NeovimHandler {}
+
+impl NeovimHandler {
+    pub fn new() -> NeovimHandler {
+        NeovimHandler {}
+    }
+
+    /// Looks `name` up in `REQUEST_HANDLERS` and runs it. None of the
+    /// current handlers need a live `nvim` connection, so this stays a
+    /// plain, synchronous, easily testable lookup.
+    fn dispatch_request(&self, name: &str, args: &[Value]) -> Result<Value, Value> {
+        match REQUEST_HANDLERS.get(name) {
+            Some(handler) => handler(args),
+            None => Err(Value::from(format!("Unknown neovide request: {}", name))),
+        }
+    }
+}
+
+#[async_trait]
+impl Handler for NeovimHandler {
+    type Writer = NeovimWriter;
+
+    async fn handle_notify(&self, _event_name: String, _arguments: Vec<Value>, _neovim: Neovim<NeovimWriter>) {
+        // Redraw events and other out-of-band notifications are handled
+        // by the grid/window layer elsewhere; nothing to do here yet.
+    }
+
+    async fn handle_request(&self, name: String, args: Vec<Value>, _neovim: Neovim<NeovimWriter>) -> Result<Value, Value> {
+        self.dispatch_request(&name, &args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ping_replies_pong() {
+        let handler = NeovimHandler::new();
+        assert_eq!(handler.dispatch_request("ping", &[]), Ok(Value::from("pong")));
+    }
+
+    #[test]
+    fn get_font_falls_back_when_env_var_unset() {
+        std::env::remove_var("NEOVIDE_FONT");
+        let handler = NeovimHandler::new();
+        assert_eq!(
+            handler.dispatch_request("neovide_get_font", &[]),
+            Ok(Value::from("Fira Code"))
+        );
+    }
+
+    #[test]
+    fn get_font_reads_env_var_when_set() {
+        std::env::set_var("NEOVIDE_FONT", "Comic Sans MS");
+        let handler = NeovimHandler::new();
+        assert_eq!(
+            handler.dispatch_request("neovide_get_font", &[]),
+            Ok(Value::from("Comic Sans MS"))
+        );
+        std::env::remove_var("NEOVIDE_FONT");
+    }
+
+    #[test]
+    fn get_scale_factor_falls_back_when_env_var_unset_or_invalid() {
+        std::env::remove_var("NEOVIDE_SCALE_FACTOR");
+        let handler = NeovimHandler::new();
+        assert_eq!(
+            handler.dispatch_request("neovide_get_scale_factor", &[]),
+            Ok(Value::from(1.0))
+        );
+
+        std::env::set_var("NEOVIDE_SCALE_FACTOR", "not-a-number");
+        assert_eq!(
+            handler.dispatch_request("neovide_get_scale_factor", &[]),
+            Ok(Value::from(1.0))
+        );
+        std::env::remove_var("NEOVIDE_SCALE_FACTOR");
+    }
+
+    #[test]
+    fn get_scale_factor_reads_env_var_when_set() {
+        std::env::set_var("NEOVIDE_SCALE_FACTOR", "2.0");
+        let handler = NeovimHandler::new();
+        assert_eq!(
+            handler.dispatch_request("neovide_get_scale_factor", &[]),
+            Ok(Value::from(2.0))
+        );
+        std::env::remove_var("NEOVIDE_SCALE_FACTOR");
+    }
+
+    #[test]
+    fn unknown_method_is_rejected() {
+        let handler = NeovimHandler::new();
+        assert_eq!(
+            handler.dispatch_request("neovide_does_not_exist", &[]),
+            Err(Value::from("Unknown neovide request: neovide_does_not_exist"))
+        );
+    }
+}
@@ -3,19 +3,31 @@ mod handler;
 mod keybindings;
 mod ui_commands;
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use std::process::Stdio;
+use std::task::{Context, Poll};
 
 use rmpv::Value;
 use nvim_rs::{create::tokio as create, UiAttachOptions, Neovim};
 use nvim_rs::compat::tokio::Compat;
+use tokio::io::{self, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio::net::tcp::OwnedWriteHalf as TcpWriteHalf;
 use tokio::runtime::Runtime;
 use tokio::process::{Command, ChildStdin};
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::watch;
+
+#[cfg(unix)]
+use tokio::net::UnixStream;
+#[cfg(unix)]
+use tokio::net::unix::OwnedWriteHalf as SocketWriteHalf;
 
 pub use events::*;
 pub use keybindings::*;
-pub use ui_commands::UiCommand;
+pub use ui_commands::{RemoteCursor, UiCommand};
 use crate::error_handling::ResultPanicExplanation;
 use crate::INITIAL_DIMENSIONS;
 use handler::NeovimHandler;
@@ -42,6 +54,118 @@ fn create_nvim_command() -> Command {
     cmd
 }
 
+/// How the bridge should reach Neovim: spawn an embedded child process (the
+/// default), or attach to an already-running instance over TCP or a
+/// Unix domain socket. Windows named pipes are not supported: on Windows,
+/// `connect` returns an `Unsupported` error for `Socket`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ConnectionMode {
+    Child,
+    Tcp(String),
+    Socket(String),
+}
+
+impl ConnectionMode {
+    /// Parsed from the environment. `NEOVIDE_CONNECT_TCP` wins if both
+    /// variables are set, mirroring `parse`'s `--nvim-tcp`-over-
+    /// `--nvim-socket` precedence below.
+    fn from_env() -> ConnectionMode {
+        if let Ok(address) = std::env::var("NEOVIDE_CONNECT_TCP") {
+            ConnectionMode::Tcp(address)
+        } else if let Ok(path) = std::env::var("NEOVIDE_CONNECT_SOCKET") {
+            ConnectionMode::Socket(path)
+        } else {
+            ConnectionMode::Child
+        }
+    }
+
+    /// Parsed from CLI arguments such as `std::env::args()` (leading
+    /// binary name included; ignored since neither flag matches it).
+    /// Accepts both `--nvim-tcp=ADDRESS` and `--nvim-tcp ADDRESS` forms
+    /// (and their `--nvim-socket` equivalents). `--nvim-tcp` wins if both
+    /// are passed, mirroring `from_env`'s precedence.
+    fn parse<I: IntoIterator<Item = String>>(args: I) -> Option<ConnectionMode> {
+        let args: Vec<String> = args.into_iter().collect();
+        if let Some(address) = find_flag_value(&args, "--nvim-tcp") {
+            Some(ConnectionMode::Tcp(address))
+        } else {
+            find_flag_value(&args, "--nvim-socket").map(ConnectionMode::Socket)
+        }
+    }
+
+    /// How `start_process` picks a `ConnectionMode`: CLI flags take
+    /// priority over environment variables, which take priority over the
+    /// `Child` default.
+    fn resolve() -> ConnectionMode {
+        ConnectionMode::parse(std::env::args()).unwrap_or_else(ConnectionMode::from_env)
+    }
+}
+
+/// Looks for `--flag=value` or `--flag value` among `args` and returns
+/// `value` from whichever form matched first.
+fn find_flag_value(args: &[String], flag: &str) -> Option<String> {
+    let prefix = format!("{}=", flag);
+    for (index, arg) in args.iter().enumerate() {
+        if let Some(value) = arg.strip_prefix(&prefix) {
+            return Some(value.to_string());
+        }
+        if arg == flag {
+            return args.get(index + 1).cloned();
+        }
+    }
+    None
+}
+
+/// The write half of whichever transport the bridge ended up connecting
+/// over, so the rest of the bridge can stay agnostic to which
+/// `ConnectionMode` was chosen.
+enum NeovimWriter {
+    Child(Compat<ChildStdin>),
+    Tcp(Compat<TcpWriteHalf>),
+    #[cfg(unix)]
+    Socket(Compat<SocketWriteHalf>),
+}
+
+impl AsyncWrite for NeovimWriter {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            NeovimWriter::Child(writer) => Pin::new(writer).poll_write(cx, buf),
+            NeovimWriter::Tcp(writer) => Pin::new(writer).poll_write(cx, buf),
+            #[cfg(unix)]
+            NeovimWriter::Socket(writer) => Pin::new(writer).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            NeovimWriter::Child(writer) => Pin::new(writer).poll_flush(cx),
+            NeovimWriter::Tcp(writer) => Pin::new(writer).poll_flush(cx),
+            #[cfg(unix)]
+            NeovimWriter::Socket(writer) => Pin::new(writer).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            NeovimWriter::Child(writer) => Pin::new(writer).poll_shutdown(cx),
+            NeovimWriter::Tcp(writer) => Pin::new(writer).poll_shutdown(cx),
+            #[cfg(unix)]
+            NeovimWriter::Socket(writer) => Pin::new(writer).poll_shutdown(cx),
+        }
+    }
+}
+
+/// The lifecycle of the connection to Neovim, mirrored out through a
+/// `watch` channel so the window layer can react to it (e.g. show a
+/// reconnect prompt) instead of the whole process going down with it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConnectionState {
+    Uninitialized,
+    InitInProgress,
+    Initialized,
+    Error(String),
+}
+
 async fn drain(receiver: &mut UnboundedReceiver<UiCommand>) -> Option<Vec<UiCommand>> {
     if let Some(ui_command) = receiver.recv().await {
         let mut results = vec![ui_command];
@@ -54,17 +178,82 @@ async fn drain(receiver: &mut UnboundedReceiver<UiCommand>) -> Option<Vec<UiComm
     }
 }
 
-async fn handle_current_commands(receiver: &mut UnboundedReceiver<UiCommand>, nvim: &Neovim<Compat<ChildStdin>>) -> bool {
-    if let Some(commands) = drain(receiver).await {
-        let (resize_list, other_commands): (Vec<UiCommand>, Vec<UiCommand>) = commands
-            .into_iter()
-            .partition(|command| command.is_resize());
-        if let Some(resize_command) = resize_list.into_iter().last() {
-            resize_command.execute(&nvim).await;
+/// Drops all but the last command sharing a `collapse_key` within a single
+/// drained batch, keeping every other command in its original position.
+/// "Latest resize wins" is now just the `CollapseKey::Resize` instance of
+/// this rule instead of its own special case.
+fn coalesce(commands: Vec<UiCommand>) -> Vec<UiCommand> {
+    let mut last_index_for_key = HashMap::new();
+    let mut scroll_tick_totals = HashMap::new();
+    for (index, command) in commands.iter().enumerate() {
+        if let Some(key) = command.collapse_key() {
+            last_index_for_key.insert(key.clone(), index);
+            if let UiCommand::Scroll { count, .. } = command {
+                *scroll_tick_totals.entry(key).or_insert(0) += count;
+            }
         }
+    }
+
+    commands
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, command)| match command.collapse_key() {
+            // Scroll ticks are cumulative, not state to overwrite: replaying
+            // only the last one would silently drop every earlier tick, so
+            // the survivor carries the summed count instead.
+            Some(key) if last_index_for_key[&key] == index => match command {
+                UiCommand::Scroll { direction, position, .. } => Some(UiCommand::Scroll {
+                    direction,
+                    position,
+                    count: scroll_tick_totals[&key],
+                }),
+                other => Some(other),
+            },
+            Some(_) => None,
+            None => Some(command),
+        })
+        .collect()
+}
 
-        for ui_command in other_commands.into_iter() {
-            ui_command.execute(&nvim).await;
+/// Spawns the worker backing collaborative cursor presence: it receives
+/// remote peers' cursor positions over `receiver` and queues a
+/// `UiCommand::GhostCursor` for each one, reusing the normal drain/execute
+/// pipeline to draw them. The transport feeding `receiver` (e.g. a TCP
+/// relay) is expected to live outside the bridge; this just wires the
+/// plumbing to consume it.
+fn start_cursor_presence(
+    ui_sender: UnboundedSender<UiCommand>,
+    cursor_sender: Arc<Mutex<Option<UnboundedSender<RemoteCursor>>>>,
+) {
+    let (sender, mut receiver) = unbounded_channel::<RemoteCursor>();
+    *cursor_sender.lock().unwrap() = Some(sender);
+
+    tokio::spawn(async move {
+        while let Some(remote_cursor) = receiver.recv().await {
+            let _ = ui_sender.send(UiCommand::GhostCursor(remote_cursor));
+        }
+    });
+}
+
+/// Drops the presence sender, which ends the worker's `recv` loop the
+/// next time it polls.
+fn stop_cursor_presence(cursor_sender: &Mutex<Option<UnboundedSender<RemoteCursor>>>) {
+    cursor_sender.lock().unwrap().take();
+}
+
+async fn handle_current_commands(
+    receiver: &mut UnboundedReceiver<UiCommand>,
+    nvim: &Neovim<NeovimWriter>,
+    ui_sender: &UnboundedSender<UiCommand>,
+    cursor_sender: &Arc<Mutex<Option<UnboundedSender<RemoteCursor>>>>,
+) -> bool {
+    if let Some(commands) = drain(receiver).await {
+        for ui_command in coalesce(commands) {
+            match ui_command {
+                UiCommand::CursorStart(_address) => start_cursor_presence(ui_sender.clone(), cursor_sender.clone()),
+                UiCommand::CursorStop => stop_cursor_presence(cursor_sender),
+                other => other.execute(&nvim).await,
+            }
         }
         true
     } else {
@@ -72,32 +261,140 @@ async fn handle_current_commands(receiver: &mut UnboundedReceiver<UiCommand>, nv
     }
 }
 
-async fn start_process(mut receiver: UnboundedReceiver<UiCommand>) {
+/// Connects to Neovim over whichever transport `mode` selects and returns
+/// the attached client together with the future driving its IO loop. Each
+/// branch wraps its transport-specific writer half in `NeovimWriter` up
+/// front, so `Neovim::new` below is always instantiated at the same `W`.
+async fn connect(mode: &ConnectionMode, handler: NeovimHandler) -> io::Result<(Neovim<NeovimWriter>, create::IoHandle)> {
+    match mode {
+        ConnectionMode::Child => {
+            let mut child = create_nvim_command()
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()?;
+            let reader = child.stdout.take().expect("child was spawned with a piped stdout");
+            let writer = NeovimWriter::Child(Compat::new(child.stdin.take().expect("child was spawned with a piped stdin")));
+            let (nvim, io_handler) = Neovim::new(Compat::new(reader), writer, handler);
+            tokio::spawn(async move { let _ = child.wait().await; });
+            Ok((nvim, io_handler))
+        }
+        ConnectionMode::Tcp(address) => {
+            let stream = TcpStream::connect(address).await?;
+            let (reader, writer) = stream.into_split();
+            let (nvim, io_handler) = Neovim::new(Compat::new(reader), NeovimWriter::Tcp(Compat::new(writer)), handler);
+            Ok((nvim, io_handler))
+        }
+        #[cfg(unix)]
+        ConnectionMode::Socket(path) => {
+            let stream = UnixStream::connect(path).await?;
+            let (reader, writer) = stream.into_split();
+            let (nvim, io_handler) = Neovim::new(Compat::new(reader), NeovimWriter::Socket(Compat::new(writer)), handler);
+            Ok((nvim, io_handler))
+        }
+        #[cfg(windows)]
+        ConnectionMode::Socket(_) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Named pipe connections are not yet wired up on Windows",
+        )),
+    }
+}
+
+/// Atomically decides what to do with `command` given the current
+/// connection state: send it straight through once `Initialized`, drop it
+/// while in an error state, or buffer it in `pending` for the eventual
+/// drain otherwise. Locking `pending` before reading `state` is what closes
+/// the race against `flip_to_initialized` below: whichever of the two gets
+/// the lock first is guaranteed to fully finish (send or buffer; drain and
+/// flip) before the other runs, so a command can never be buffered after
+/// the last drain has already happened.
+fn queue_or_buffer(
+    command: UiCommand,
+    pending: &Mutex<Vec<UiCommand>>,
+    state: &watch::Receiver<ConnectionState>,
+    sender: &UnboundedSender<UiCommand>,
+) {
+    let mut pending = pending.lock().unwrap();
+    match &*state.borrow() {
+        ConnectionState::Initialized => {
+            drop(pending);
+            sender.send(command)
+                .unwrap_or_explained_panic(
+                    "Could Not Send UI Command",
+                    "Could not send UI command from the window system to the neovim process.");
+        }
+        ConnectionState::Error(message) => {
+            eprintln!("Dropping UI command, neovim bridge is in an error state: {}", message);
+        }
+        ConnectionState::Uninitialized | ConnectionState::InitInProgress => {
+            pending.push(command);
+        }
+    }
+}
+
+/// Drains everything buffered in `pending` into `sender` and then flips
+/// `state` to `Initialized`, holding `pending`'s lock across both steps.
+/// See `queue_or_buffer` for why that matters.
+fn flip_to_initialized(
+    pending: &Mutex<Vec<UiCommand>>,
+    state_sender: &watch::Sender<ConnectionState>,
+    sender: &UnboundedSender<UiCommand>,
+) {
+    let mut pending = pending.lock().unwrap();
+    for command in pending.drain(..) {
+        let _ = sender.send(command);
+    }
+    let _ = state_sender.send(ConnectionState::Initialized);
+}
+
+async fn start_process(
+    mut receiver: UnboundedReceiver<UiCommand>,
+    sender: UnboundedSender<UiCommand>,
+    pending: Arc<Mutex<Vec<UiCommand>>>,
+    state_sender: watch::Sender<ConnectionState>,
+    cursor_sender: Arc<Mutex<Option<UnboundedSender<RemoteCursor>>>>,
+) {
     let (width, height) = INITIAL_DIMENSIONS;
-    let (mut nvim, io_handler, _) = create::new_child_cmd(&mut create_nvim_command(), NeovimHandler::new()).await
-        .unwrap_or_explained_panic("Could not create nvim process", "Could not locate or start the neovim process");
+    let _ = state_sender.send(ConnectionState::InitInProgress);
+
+    let connection_mode = ConnectionMode::resolve();
+    let (mut nvim, io_handler) = match connect(&connection_mode, NeovimHandler::new()).await {
+        Ok(connected) => connected,
+        Err(error) => {
+            let _ = state_sender.send(ConnectionState::Error(format!("Could not connect to neovim: {}", error)));
+            return;
+        }
+    };
 
+    let io_loop_state_sender = state_sender.clone();
     tokio::spawn(async move {
-        match io_handler.await {
-            Err(join_error) => eprintln!("Error joining IO loop: '{}'", join_error),
-            Ok(Err(error)) => eprintln!("Error: '{}'", error),
-            Ok(Ok(())) => {}
+        let error = match io_handler.await {
+            Err(join_error) => format!("Error joining IO loop: '{}'", join_error),
+            Ok(Err(error)) => format!("Error: '{}'", error),
+            Ok(Ok(())) => return,
         };
-        std::process::exit(0);
+        let _ = io_loop_state_sender.send(ConnectionState::Error(error));
     });
 
-    nvim.set_var("neovide", Value::Boolean(true)).await
-        .unwrap_or_explained_panic("Could not communicate.", "Could not communicate with neovim process");
+    if let Err(error) = nvim.set_var("neovide", Value::Boolean(true)).await {
+        let _ = state_sender.send(ConnectionState::Error(format!("Could not communicate with neovim process: {}", error)));
+        return;
+    }
+
     let mut options = UiAttachOptions::new();
     options.set_linegrid_external(true);
     options.set_rgb(true);
-    nvim.ui_attach(width as i64, height as i64, &options).await
-        .unwrap_or_explained_panic("Could not attach.", "Could not attach ui to neovim process");
+    if let Err(error) = nvim.ui_attach(width as i64, height as i64, &options).await {
+        let _ = state_sender.send(ConnectionState::Error(format!("Could not attach ui to neovim process: {}", error)));
+        return;
+    }
+
+    flip_to_initialized(&pending, &state_sender, &sender);
 
     let nvim = Arc::new(nvim);
+    let ui_sender = sender.clone();
     tokio::spawn(async move {
         loop {
-            if !handle_current_commands(&mut receiver, &nvim).await {
+            if !handle_current_commands(&mut receiver, &nvim, &ui_sender, &cursor_sender).await {
                 break;
             }
         }
@@ -106,25 +403,242 @@ async fn start_process(mut receiver: UnboundedReceiver<UiCommand>) {
 
 pub struct Bridge {
     _runtime: Runtime,
-    sender: UnboundedSender<UiCommand>
+    sender: UnboundedSender<UiCommand>,
+    pending: Arc<Mutex<Vec<UiCommand>>>,
+    state: watch::Receiver<ConnectionState>,
+    cursor_sender: Arc<Mutex<Option<UnboundedSender<RemoteCursor>>>>,
 }
 
 impl Bridge {
     pub fn new() -> Bridge {
-        let mut runtime = Runtime::new().unwrap();
+        let runtime = Runtime::new().unwrap();
         let (sender, receiver) = unbounded_channel::<UiCommand>();
+        let pending = Arc::new(Mutex::new(Vec::new()));
+        let (state_sender, state_receiver) = watch::channel(ConnectionState::Uninitialized);
+        let cursor_sender = Arc::new(Mutex::new(None));
 
-        runtime.block_on(async move {
-            start_process(receiver).await;
+        let sender_for_process = sender.clone();
+        let pending_for_process = pending.clone();
+        let cursor_sender_for_process = cursor_sender.clone();
+        runtime.spawn(async move {
+            start_process(receiver, sender_for_process, pending_for_process, state_sender, cursor_sender_for_process).await;
         });
 
-        Bridge { _runtime: runtime, sender }
+        Bridge { _runtime: runtime, sender, pending, state: state_receiver, cursor_sender }
+    }
+
+    /// Pushes a remote peer's cursor position in for the presence worker to
+    /// draw, if the worker is currently running.
+    pub fn push_remote_cursor(&self, remote_cursor: RemoteCursor) {
+        if let Some(sender) = self.cursor_sender.lock().unwrap().as_ref() {
+            let _ = sender.send(remote_cursor);
+        }
+    }
+
+    /// The current connection state, e.g. to decide whether to show a
+    /// reconnect prompt.
+    pub fn state(&self) -> ConnectionState {
+        self.state.borrow().clone()
+    }
+
+    /// Lets the window layer observe connection state changes as they
+    /// happen, rather than polling `state`.
+    pub fn subscribe(&self) -> watch::Receiver<ConnectionState> {
+        self.state.clone()
     }
 
     pub fn queue_command(&self, command: UiCommand) {
-        self.sender.send(command)
-            .unwrap_or_explained_panic(
-                "Could Not Send UI Command", 
-                "Could not send UI command from the window system to the neovim process.");
+        queue_or_buffer(command, &self.pending, &self.state, &self.sender);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyboard(input: &str) -> UiCommand {
+        UiCommand::Keyboard(input.to_string())
+    }
+
+    fn resize(width: u64, height: u64) -> UiCommand {
+        UiCommand::Resize { width, height }
+    }
+
+    fn drag(x: u64, y: u64) -> UiCommand {
+        UiCommand::Drag(x, y)
+    }
+
+    fn scroll(direction: &str, position: (u64, u64), count: u32) -> UiCommand {
+        UiCommand::Scroll { direction: direction.to_string(), position, count }
+    }
+
+    #[test]
+    fn coalesce_keeps_only_the_last_resize() {
+        let commands = vec![resize(10, 10), resize(20, 20), resize(30, 30)];
+        assert_eq!(coalesce(commands), vec![resize(30, 30)]);
+    }
+
+    #[test]
+    fn coalesce_accumulates_repeated_scroll_ticks_instead_of_dropping_them() {
+        let commands = vec![scroll("up", (0, 0), 1), scroll("up", (0, 0), 1), scroll("up", (0, 0), 1)];
+        assert_eq!(coalesce(commands), vec![scroll("up", (0, 0), 3)]);
+    }
+
+    #[test]
+    fn coalesce_keeps_opposite_scroll_directions_separate() {
+        let commands = vec![scroll("up", (0, 0), 2), scroll("down", (0, 0), 1)];
+        assert_eq!(coalesce(commands), vec![scroll("up", (0, 0), 2), scroll("down", (0, 0), 1)]);
+    }
+
+    #[test]
+    fn coalesce_merges_each_collapse_key_independently() {
+        let commands = vec![drag(0, 0), resize(10, 10), drag(1, 1), resize(20, 20), drag(2, 2)];
+        assert_eq!(coalesce(commands), vec![resize(20, 20), drag(2, 2)]);
+    }
+
+    #[test]
+    fn coalesce_leaves_order_sensitive_commands_untouched() {
+        let commands = vec![keyboard("i"), keyboard("h"), keyboard("i")];
+        assert_eq!(coalesce(commands.clone()), commands);
+    }
+
+    #[test]
+    fn coalesce_preserves_relative_order_of_surviving_commands() {
+        let commands = vec![keyboard("i"), drag(0, 0), resize(10, 10), drag(1, 1), keyboard("escape")];
+        assert_eq!(
+            coalesce(commands),
+            vec![keyboard("i"), resize(10, 10), drag(1, 1), keyboard("escape")]
+        );
+    }
+
+    #[tokio::test]
+    async fn drain_collects_everything_queued_without_blocking() {
+        let (sender, mut receiver) = unbounded_channel::<UiCommand>();
+        sender.send(keyboard("a")).unwrap();
+        sender.send(keyboard("b")).unwrap();
+
+        let drained = drain(&mut receiver).await.unwrap();
+
+        assert_eq!(drained, vec![keyboard("a"), keyboard("b")]);
+    }
+
+    #[tokio::test]
+    async fn drain_returns_none_once_the_sender_is_dropped() {
+        let (sender, mut receiver) = unbounded_channel::<UiCommand>();
+        drop(sender);
+
+        assert_eq!(drain(&mut receiver).await, None);
+    }
+
+    // Regression test for a race where a command queued right around
+    // startup could vanish forever: if `queue_or_buffer` read `state` as
+    // not-yet-`Initialized` but `flip_to_initialized` had already finished
+    // draining `pending` by the time the command was pushed, that command
+    // would sit in `pending` with nobody left to drain it. Runs on plain
+    // threads (no tokio runtime needed) since every operation involved -
+    // mutex locks, `UnboundedSender::send`, `watch::Sender::send` - is
+    // synchronous.
+    #[test]
+    fn queue_or_buffer_races_flip_to_initialized_without_losing_a_command() {
+        use std::thread;
+
+        const COMMAND_COUNT: usize = 8;
+
+        for _ in 0..100 {
+            let (sender, mut receiver) = unbounded_channel::<UiCommand>();
+            let pending: Arc<Mutex<Vec<UiCommand>>> = Arc::new(Mutex::new(Vec::new()));
+            let (state_sender, state_receiver) = watch::channel(ConnectionState::InitInProgress);
+
+            let mut handles = Vec::new();
+            for i in 0..COMMAND_COUNT {
+                let pending = pending.clone();
+                let state_receiver = state_receiver.clone();
+                let sender = sender.clone();
+                handles.push(thread::spawn(move || {
+                    queue_or_buffer(keyboard(&i.to_string()), &pending, &state_receiver, &sender);
+                }));
+            }
+            let flip_pending = pending.clone();
+            let flip_sender = sender.clone();
+            handles.push(thread::spawn(move || {
+                flip_to_initialized(&flip_pending, &state_sender, &flip_sender);
+            }));
+
+            for handle in handles {
+                handle.join().unwrap();
+            }
+
+            let mut received = Vec::new();
+            while let Ok(command) = receiver.try_recv() {
+                received.push(command);
+            }
+            assert_eq!(received.len(), COMMAND_COUNT, "a command vanished during the init race");
+        }
+    }
+
+    fn args(flags: &[&str]) -> Vec<String> {
+        std::iter::once("neovide".to_string())
+            .chain(flags.iter().map(|flag| flag.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_reads_the_equals_form() {
+        assert_eq!(
+            ConnectionMode::parse(args(&["--nvim-tcp=127.0.0.1:6666"])),
+            Some(ConnectionMode::Tcp("127.0.0.1:6666".to_string()))
+        );
+        assert_eq!(
+            ConnectionMode::parse(args(&["--nvim-socket=/tmp/nvim.sock"])),
+            Some(ConnectionMode::Socket("/tmp/nvim.sock".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_reads_the_space_separated_form() {
+        assert_eq!(
+            ConnectionMode::parse(args(&["--nvim-tcp", "127.0.0.1:6666"])),
+            Some(ConnectionMode::Tcp("127.0.0.1:6666".to_string()))
+        );
+        assert_eq!(
+            ConnectionMode::parse(args(&["--nvim-socket", "/tmp/nvim.sock"])),
+            Some(ConnectionMode::Socket("/tmp/nvim.sock".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_prefers_tcp_over_socket_regardless_of_order() {
+        assert_eq!(
+            ConnectionMode::parse(args(&["--nvim-socket=/tmp/nvim.sock", "--nvim-tcp=127.0.0.1:6666"])),
+            Some(ConnectionMode::Tcp("127.0.0.1:6666".to_string()))
+        );
+        assert_eq!(
+            ConnectionMode::parse(args(&["--nvim-tcp=127.0.0.1:6666", "--nvim-socket=/tmp/nvim.sock"])),
+            Some(ConnectionMode::Tcp("127.0.0.1:6666".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_returns_none_with_neither_flag() {
+        assert_eq!(ConnectionMode::parse(args(&["--some-other-flag"])), None);
+    }
+
+    #[test]
+    fn from_env_prefers_tcp_over_socket_when_both_are_set() {
+        std::env::set_var("NEOVIDE_CONNECT_TCP", "127.0.0.1:6666");
+        std::env::set_var("NEOVIDE_CONNECT_SOCKET", "/tmp/nvim.sock");
+
+        assert_eq!(ConnectionMode::from_env(), ConnectionMode::Tcp("127.0.0.1:6666".to_string()));
+
+        std::env::remove_var("NEOVIDE_CONNECT_TCP");
+        std::env::remove_var("NEOVIDE_CONNECT_SOCKET");
+    }
+
+    #[test]
+    fn from_env_defaults_to_child_with_neither_var_set() {
+        std::env::remove_var("NEOVIDE_CONNECT_TCP");
+        std::env::remove_var("NEOVIDE_CONNECT_SOCKET");
+
+        assert_eq!(ConnectionMode::from_env(), ConnectionMode::Child);
     }
 }
@@ -0,0 +1,171 @@
+use nvim_rs::Neovim;
+use rmpv::Value;
+
+use super::NeovimWriter;
+
+/// Identifies commands that can be collapsed together within a single
+/// drained batch. Commands sharing a key are deduplicated down to the
+/// last one seen (for `Scroll`, that survivor carries the summed tick
+/// count); commands with no key (`None`) always pass through.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CollapseKey {
+    Resize,
+    Drag,
+    /// Keyed by direction and position so only repeats of the exact same
+    /// scroll get folded together - an up-tick must never be merged away
+    /// by a later down-tick.
+    Scroll(String, (u64, u64)),
+    FocusState,
+    GhostCursor(String),
+}
+
+/// A peer's cursor position within a shared buffer, as received from the
+/// presence relay.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RemoteCursor {
+    pub peer: String,
+    pub buffer: i64,
+    pub row: u64,
+    pub col: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum UiCommand {
+    Quit,
+    Resize { width: u64, height: u64 },
+    Keyboard(String),
+    MouseButton { action: String, position: (u64, u64) },
+    Drag(u64, u64),
+    /// `count` is the number of wheel ticks this command represents, so
+    /// coalescing repeated scrolls can accumulate it instead of dropping
+    /// ticks on the floor.
+    Scroll { direction: String, position: (u64, u64), count: u32 },
+    FocusLost,
+    FocusGained,
+    FileDrop(String),
+    /// Starts the cursor/presence worker. The bridge does not connect to
+    /// `address`; the worker only consumes updates pushed in through
+    /// `Bridge::push_remote_cursor`.
+    CursorStart(String),
+    /// Tears down the cursor/presence worker, if running.
+    CursorStop,
+    /// A peer's cursor moved; draw or move their ghost cursor.
+    GhostCursor(RemoteCursor),
+}
+
+impl UiCommand {
+    pub fn is_resize(&self) -> bool {
+        matches!(self, UiCommand::Resize { .. })
+    }
+
+    /// Successive commands sharing a collapse key are merged down to the
+    /// last one within a single drained batch; `None` means the command
+    /// is order-sensitive and always passes through untouched.
+    pub fn collapse_key(&self) -> Option<CollapseKey> {
+        match self {
+            UiCommand::Resize { .. } => Some(CollapseKey::Resize),
+            UiCommand::Drag(..) => Some(CollapseKey::Drag),
+            UiCommand::Scroll { direction, position, .. } => Some(CollapseKey::Scroll(direction.clone(), *position)),
+            UiCommand::FocusLost | UiCommand::FocusGained => Some(CollapseKey::FocusState),
+            UiCommand::GhostCursor(remote_cursor) => Some(CollapseKey::GhostCursor(remote_cursor.peer.clone())),
+            UiCommand::Quit
+            | UiCommand::Keyboard(..)
+            | UiCommand::MouseButton { .. }
+            | UiCommand::FileDrop(..)
+            | UiCommand::CursorStart(..)
+            | UiCommand::CursorStop => None,
+        }
+    }
+
+    pub async fn execute(self, nvim: &Neovim<NeovimWriter>) {
+        match self {
+            UiCommand::Quit => {
+                let _ = nvim.command("qa!").await;
+            }
+            UiCommand::Resize { width, height } => {
+                let _ = nvim.ui_try_resize(width as i64, height as i64).await;
+            }
+            UiCommand::Keyboard(input) => {
+                let _ = nvim.input(&input).await;
+            }
+            UiCommand::MouseButton { action, position: (grid_x, grid_y) } => {
+                let _ = nvim
+                    .input_mouse("left", &action, "", 0, grid_y as i64, grid_x as i64)
+                    .await;
+            }
+            UiCommand::Drag(grid_x, grid_y) => {
+                let _ = nvim
+                    .input_mouse("left", "drag", "", 0, grid_y as i64, grid_x as i64)
+                    .await;
+            }
+            UiCommand::Scroll { direction, position: (grid_x, grid_y), count } => {
+                for _ in 0..count {
+                    let _ = nvim
+                        .input_mouse("wheel", &direction, "", 0, grid_y as i64, grid_x as i64)
+                        .await;
+                }
+            }
+            UiCommand::FocusLost => {
+                let _ = nvim.command("if exists('#FocusLost') | doautocmd FocusLost | endif").await;
+            }
+            UiCommand::FocusGained => {
+                let _ = nvim.command("if exists('#FocusGained') | doautocmd FocusGained | endif").await;
+            }
+            UiCommand::FileDrop(path) => {
+                let _ = nvim.command(&format!("edit {}", path)).await;
+            }
+            // Intercepted by the bridge's drain loop before reaching here;
+            // they control the presence worker rather than talking to nvim.
+            UiCommand::CursorStart(..) | UiCommand::CursorStop => {}
+            UiCommand::GhostCursor(remote_cursor) => {
+                let args = ghost_cursor_call_args(&remote_cursor);
+                let _ = nvim.call_function("Neovide_set_ghost_cursor", args).await;
+            }
+        }
+    }
+}
+
+/// Builds the `Neovide_set_ghost_cursor` call arguments for a remote
+/// cursor update. `peer` is network-sourced from the presence relay, so
+/// it must be passed as a real msgpack-rpc argument here rather than
+/// interpolated into a `:call` string - otherwise a peer name containing
+/// a quote could break out and run arbitrary Ex/shell commands in every
+/// other participant's Neovim. Broken out so that marshalling can be
+/// tested without a live nvim connection.
+fn ghost_cursor_call_args(remote_cursor: &RemoteCursor) -> Vec<Value> {
+    vec![
+        Value::from(remote_cursor.peer.clone()),
+        Value::from(remote_cursor.buffer),
+        Value::from(remote_cursor.row),
+        Value::from(remote_cursor.col),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ghost_cursor_call_args_keeps_a_hostile_peer_name_as_one_opaque_argument() {
+        let remote_cursor = RemoteCursor {
+            peer: "evil'); call system('rm -rf /'); call Foo('".to_string(),
+            buffer: 1,
+            row: 2,
+            col: 3,
+        };
+
+        let args = ghost_cursor_call_args(&remote_cursor);
+
+        // The peer name must survive byte-for-byte as a single Value::String
+        // element - if it were ever interpolated into a formatted `:call`
+        // command again, this would fail because the quote/parenthesis
+        // characters would have split it into multiple arguments or broken
+        // the call outright.
+        assert_eq!(args.len(), 4);
+        assert_eq!(args[0], Value::from(remote_cursor.peer.clone()));
+        assert_eq!(args[0].as_str(), Some(remote_cursor.peer.as_str()));
+        assert_eq!(args[1], Value::from(1));
+        assert_eq!(args[2], Value::from(2));
+        assert_eq!(args[3], Value::from(3));
+    }
+}